@@ -4,124 +4,695 @@
     clippy::cast_possible_truncation,
     clippy::cast_precision_loss
 )]
-use std::cmp::Ordering;
+use std::{cmp::Ordering, collections::HashMap};
 
 use bevy::{
     prelude::*,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_resource::{
+            AsBindGroup, BlendComponent, BlendFactor, BlendOperation, BlendState,
+            RenderPipelineDescriptor, ShaderRef, ShaderType, SpecializedMeshPipelineError,
+        },
+    },
+    sprite::{Material2d, Material2dKey, Material2dPlugin, MaterialMesh2dBundle, Mesh2dHandle},
     window::{close_on_esc, WindowMode}, gizmos,
 };
 use rand::{distributions::Uniform, prelude::Distribution, random, thread_rng};
+use serde::Deserialize;
 
-#[derive(Resource)]
-struct Settings {
+/// All runtime-tunable parameters, deserialized from `config.ron` next to the
+/// executable so the simulation can be retuned without recompiling.
+#[derive(Resource, Deserialize, Clone)]
+#[serde(default)]
+struct SimConfig {
     speed: f32,
+    entity_count: usize,
+    spawn_radius_min: f32,
+    spawn_radius_max: f32,
+    interaction_radius: f32,
+    clamp_bound: f32,
+    separation_strength: f32,
+    /// Name of the colour-scheme theme active at launch.
+    theme: String,
+    /// Render mode active at launch: "glyph" (letters) or "shape" (vector meshes).
+    render_mode: String,
+    /// How shape-mode meshes are filled.
+    fill: Fill,
+    /// How shape-mode meshes are blended with the frame.
+    blend: BlendMode,
+    /// The roster of species, in index order; `Species(i)` refers to the ith entry.
+    species: Vec<SpeciesConfig>,
+    /// Optional N×N dominance table; when omitted a symmetric cycle is derived.
+    beats: Option<Vec<Vec<bool>>>,
 }
 
-#[derive(Component, Clone, Copy)]
-enum Shape {
-    Scissors,
-    Paper,
-    Rock,
+/// How a single species is named and drawn: its display name, colour (RGBA),
+/// glyph, and font asset.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+struct SpeciesConfig {
+    name: String,
+    color: [f32; 4],
+    glyph: String,
+    font: String,
 }
 
-impl PartialOrd for Shape {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl SpeciesConfig {
+    fn color(&self) -> Color {
+        Color::rgba(self.color[0], self.color[1], self.color[2], self.color[3])
     }
 }
 
-impl Ord for Shape {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match (self, other) {
-            (Self::Scissors, Self::Paper)
-            | (Self::Paper, Self::Rock)
-            | (Self::Rock, Self::Scissors) => Ordering::Greater,
-            (Self::Scissors, Self::Rock)
-            | (Self::Paper, Self::Scissors)
-            | (Self::Rock, Self::Paper) => Ordering::Less,
-            _ => Ordering::Equal,
+/// How a shape-mode mesh is filled: a flat colour, or a radial gradient from
+/// the species colour at the centre to `edge` (RGBA) at the rim.
+#[derive(Deserialize, Clone, Copy)]
+enum Fill {
+    Flat,
+    RadialGradient { edge: [f32; 4] },
+}
+
+/// How a shape-mode mesh is composited onto the frame.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+enum BlendMode {
+    /// Standard alpha "over" compositing.
+    Over,
+    /// Additive blending, so overlapping swarms glow.
+    Additive,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            speed: 100.,
+            entity_count: 1000,
+            spawn_radius_min: 100.,
+            spawn_radius_max: 800.,
+            interaction_radius: 20.,
+            clamp_bound: 800.,
+            separation_strength: 60.,
+            theme: "classic".into(),
+            render_mode: "glyph".into(),
+            fill: Fill::Flat,
+            blend: BlendMode::Over,
+            // The classic rock-paper-scissors roster, in the cyclic order the
+            // derived dominance table expects (each beats the next).
+            species: vec![
+                SpeciesConfig {
+                    name: "scissors".into(),
+                    color: [1., 0., 0., 1.],
+                    glyph: "s".into(),
+                    font: "SF-Pro.ttf".into(),
+                },
+                SpeciesConfig {
+                    name: "paper".into(),
+                    color: [1., 1., 1., 1.],
+                    glyph: "p".into(),
+                    font: "SF-Pro.ttf".into(),
+                },
+                SpeciesConfig {
+                    name: "rock".into(),
+                    color: [0.5, 0.5, 0.5, 1.],
+                    glyph: "r".into(),
+                    font: "SF-Pro.ttf".into(),
+                },
+            ],
+            beats: None,
         }
     }
 }
 
-impl PartialEq for Shape {
-    fn eq(&self, other: &Self) -> bool {
-        self.partial_cmp(other) == Some(Ordering::Equal)
+impl Default for SpeciesConfig {
+    fn default() -> Self {
+        Self {
+            name: "unnamed".into(),
+            color: [1., 1., 1., 1.],
+            glyph: "?".into(),
+            font: "SF-Pro.ttf".into(),
+        }
     }
 }
 
-impl Eq for Shape {}
+impl SimConfig {
+    /// Load `config.ron` from beside the executable, falling back to the
+    /// built-in defaults when it is missing or unreadable.
+    fn load() -> Self {
+        std::env::current_exe()
+            .ok()
+            .and_then(|path| path.parent().map(|parent| parent.join("config.ron")))
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|text| ron::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+}
 
-fn startup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.spawn(Camera2dBundle::default());
-    let mut rng = thread_rng();
-    for _ in 0..1000 {
-        let r#type = match Uniform::new(0, 3).sample(&mut rng) {
-            0 => Shape::Scissors,
-            1 => Shape::Paper,
-            2 => Shape::Rock,
-            _ => unreachable!(),
+/// A combatant's species, indexing into the [`Roster`] resource.
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+struct Species(usize);
+
+/// The active roster: the appearance of each species plus the N×N dominance
+/// table driving every comparison in [`simulate`].
+#[derive(Resource)]
+struct Roster {
+    species: Vec<SpeciesConfig>,
+    beats: Vec<Vec<bool>>,
+}
+
+impl Roster {
+    /// Build the roster from `config`, deriving a symmetric dominance cycle
+    /// when the config supplies no explicit table and validating that no pair
+    /// of species beats each other.
+    fn from_config(config: &SimConfig) -> Self {
+        let n = config.species.len();
+        let beats = config
+            .beats
+            .clone()
+            .unwrap_or_else(|| Self::default_cycle(n));
+        for (a, row) in beats.iter().enumerate() {
+            for (b, &beats_ab) in row.iter().enumerate() {
+                assert!(
+                    !(beats_ab && beats[b][a]),
+                    "dominance table is inconsistent: species {a} and {b} both beat each other",
+                );
+            }
+        }
+        Self {
+            species: config.species.clone(),
+            beats,
+        }
+    }
+
+    /// The symmetric cycle where species `i` beats the next `(n - 1) / 2`
+    /// species modulo `n`.
+    fn default_cycle(n: usize) -> Vec<Vec<bool>> {
+        let mut beats = vec![vec![false; n]; n];
+        for (a, row) in beats.iter_mut().enumerate() {
+            for k in 1..=(n - 1) / 2 {
+                row[(a + k) % n] = true;
+            }
+        }
+        beats
+    }
+
+    fn len(&self) -> usize {
+        self.species.len()
+    }
+
+    /// Whether species `a` dominates species `b`.
+    fn beats(&self, a: Species, b: Species) -> bool {
+        self.beats[a.0][b.0]
+    }
+
+    /// The dominance relation of `a` against `b`: `Greater` if `a` beats `b`,
+    /// `Less` if `b` beats `a`, `Equal` otherwise.
+    fn order(&self, a: Species, b: Species) -> Ordering {
+        if self.beats(a, b) {
+            Ordering::Greater
+        } else if self.beats(b, a) {
+            Ordering::Less
+        } else {
+            Ordering::Equal
+        }
+    }
+}
+
+/// A named colour scheme: a background plus one colour per species (indexed
+/// modulo the palette length so it covers rosters of any size).
+#[derive(Clone)]
+struct Theme {
+    name: &'static str,
+    background: Color,
+    colors: Vec<Color>,
+}
+
+impl Theme {
+    /// The colour this theme assigns to species `index`.
+    fn color(&self, index: usize) -> Color {
+        self.colors[index % self.colors.len()]
+    }
+}
+
+/// The set of available themes together with the one currently active.
+#[derive(Resource)]
+struct Themes {
+    list: Vec<Theme>,
+    active: usize,
+}
+
+impl Themes {
+    /// Assemble the built-in themes. The first, "classic", mirrors the colours
+    /// configured on the roster so the default launch looks unchanged.
+    fn new(config: &SimConfig, roster: &Roster) -> Self {
+        let classic = Theme {
+            name: "classic",
+            background: Color::BLACK,
+            colors: roster.species.iter().map(SpeciesConfig::color).collect(),
+        };
+        let mono = Theme {
+            name: "mono",
+            background: Color::BLACK,
+            colors: vec![
+                Color::WHITE,
+                Color::rgb_u8(170, 170, 170),
+                Color::rgb_u8(110, 110, 110),
+                Color::rgb_u8(210, 210, 210),
+            ],
+        };
+        let solarized = Theme {
+            name: "solarized",
+            background: Color::rgb_u8(0, 43, 54),
+            colors: vec![
+                Color::rgb_u8(220, 50, 47),
+                Color::rgb_u8(133, 153, 0),
+                Color::rgb_u8(38, 139, 210),
+                Color::rgb_u8(181, 137, 0),
+                Color::rgb_u8(211, 54, 130),
+            ],
         };
-        commands.spawn((
-            r#type,
-            Text2dBundle {
-                transform: Transform::from_translation({
-                    let theta = (Uniform::new(0., 360.).sample(&mut rng) as f32).to_radians();
-                    let radius = Uniform::new(100., 800.).sample(&mut rng);
-                    Vec3::new(radius * theta.cos(), radius * theta.sin(), 0.0)
-                }),
-                text_anchor: bevy::sprite::Anchor::Center,
-                text: Text::from_section(
-                    match r#type {
-                        Shape::Scissors => "s",
-                        Shape::Paper => "p",
-                        Shape::Rock => "r",
+        let neon = Theme {
+            name: "neon",
+            background: Color::BLACK,
+            colors: vec![
+                Color::rgb_u8(255, 0, 170),
+                Color::rgb_u8(0, 255, 200),
+                Color::rgb_u8(160, 255, 0),
+                Color::rgb_u8(255, 230, 0),
+                Color::rgb_u8(120, 80, 255),
+            ],
+        };
+        let list = vec![classic, mono, solarized, neon];
+        let active = list
+            .iter()
+            .position(|theme| theme.name == config.theme)
+            .unwrap_or(0);
+        Self { list, active }
+    }
+
+    fn current(&self) -> &Theme {
+        &self.list[self.active]
+    }
+
+    /// Advance to the next theme, wrapping around.
+    fn cycle(&mut self) {
+        self.active = (self.active + 1) % self.list.len();
+    }
+}
+
+/// The render mode in effect: glyph letters or GPU-drawn vector shapes.
+#[derive(Resource, Clone, Copy, PartialEq, Eq)]
+enum RenderMode {
+    Glyph,
+    Shape,
+}
+
+impl RenderMode {
+    fn from_config(config: &SimConfig) -> Self {
+        if config.render_mode == "shape" {
+            Self::Shape
+        } else {
+            Self::Glyph
+        }
+    }
+}
+
+/// The polygon drawn for a species, assigned cyclically by index so classic
+/// rock-paper-scissors reads as circle / square / triangle.
+fn shape_mesh(index: usize) -> Mesh {
+    const RADIUS: f32 = 10.;
+    match index % 3 {
+        0 => Mesh::from(shape::RegularPolygon::new(RADIUS, 3)),
+        1 => Mesh::from(shape::Quad::new(Vec2::splat(RADIUS * 1.6))),
+        _ => Mesh::from(shape::Circle::new(RADIUS)),
+    }
+}
+
+/// The fill uniform handed to the shape shader.
+#[derive(Clone, ShaderType)]
+struct ShapeFill {
+    color: Vec4,
+    edge_color: Vec4,
+    gradient: f32,
+}
+
+/// A 2D material that fills a mesh with either a flat colour or a radial
+/// gradient, composited with the configured blend mode.
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+#[bind_group_data(ShapeMaterialKey)]
+struct ShapeMaterial {
+    #[uniform(0)]
+    fill: ShapeFill,
+    blend: BlendMode,
+}
+
+/// Pipeline-specialization key: only the blend mode changes the pipeline.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ShapeMaterialKey {
+    blend: BlendMode,
+}
+
+impl From<&ShapeMaterial> for ShapeMaterialKey {
+    fn from(material: &ShapeMaterial) -> Self {
+        Self {
+            blend: material.blend,
+        }
+    }
+}
+
+impl ShapeMaterial {
+    fn new(color: Color, fill: Fill, blend: BlendMode) -> Self {
+        let (edge_color, gradient) = match fill {
+            Fill::Flat => (color, 0.),
+            Fill::RadialGradient { edge } => {
+                (Color::rgba(edge[0], edge[1], edge[2], edge[3]), 1.)
+            }
+        };
+        Self {
+            fill: ShapeFill {
+                color: color.rgba_to_vec4(),
+                edge_color: edge_color.rgba_to_vec4(),
+                gradient,
+            },
+            blend,
+        }
+    }
+}
+
+impl Material2d for ShapeMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shapes.wgsl".into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        if let Some(target) = descriptor
+            .fragment
+            .as_mut()
+            .and_then(|fragment| fragment.targets.get_mut(0))
+            .and_then(Option::as_mut)
+        {
+            target.blend = Some(match key.bind_group_data.blend {
+                BlendMode::Over => BlendState::ALPHA_BLENDING,
+                BlendMode::Additive => BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::SrcAlpha,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
                     },
-                    TextStyle {
-                        font: asset_server.load("SF-Pro.ttf"),
-                        font_size: 20.0,
-                        color: match r#type {
-                            Shape::Scissors => Color::RED,
-                            Shape::Paper => Color::WHITE,
-                            Shape::Rock => Color::GRAY,
-                        },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
                     },
-                )
-                .with_alignment(TextAlignment::Center),
-                ..default()
-            },
-        ));
+                },
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Shared per-species mesh and material handles used in shape mode.
+#[derive(Resource)]
+struct ShapeAssets {
+    meshes: Vec<Mesh2dHandle>,
+    materials: Vec<Handle<ShapeMaterial>>,
+}
+
+impl ShapeAssets {
+    fn build(
+        config: &SimConfig,
+        roster: &Roster,
+        theme: &Theme,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<ShapeMaterial>,
+    ) -> Self {
+        let mut mesh_handles = Vec::with_capacity(roster.len());
+        let mut material_handles = Vec::with_capacity(roster.len());
+        for index in 0..roster.len() {
+            mesh_handles.push(Mesh2dHandle(meshes.add(shape_mesh(index))));
+            material_handles.push(materials.add(ShapeMaterial::new(
+                theme.color(index),
+                config.fill,
+                config.blend,
+            )));
+        }
+        Self {
+            meshes: mesh_handles,
+            materials: material_handles,
+        }
+    }
+}
+
+/// Spawn one combatant in the active render mode, reusing the shared shape
+/// assets when drawing meshes.
+fn spawn_combatant(
+    commands: &mut Commands,
+    mode: RenderMode,
+    species: Species,
+    transform: Transform,
+    roster: &Roster,
+    theme: &Theme,
+    shapes: &ShapeAssets,
+    asset_server: &AssetServer,
+) {
+    match mode {
+        RenderMode::Glyph => {
+            let config = &roster.species[species.0];
+            commands.spawn((
+                species,
+                Text2dBundle {
+                    transform,
+                    text_anchor: bevy::sprite::Anchor::Center,
+                    text: Text::from_section(
+                        config.glyph.clone(),
+                        TextStyle {
+                            font: asset_server.load(&config.font),
+                            font_size: 20.0,
+                            color: theme.color(species.0),
+                        },
+                    )
+                    .with_alignment(TextAlignment::Center),
+                    ..default()
+                },
+            ));
+        }
+        RenderMode::Shape => {
+            commands.spawn((
+                species,
+                MaterialMesh2dBundle {
+                    mesh: shapes.meshes[species.0].clone(),
+                    material: shapes.materials[species.0].clone(),
+                    transform,
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+/// Repaint each converted glyph to its new species' letter and colour.
+fn sync_glyphs(
+    mut entities: Query<(&Species, &mut Text), Changed<Species>>,
+    roster: Res<Roster>,
+    themes: Res<Themes>,
+) {
+    let theme = themes.current();
+    for (species, mut text) in &mut entities {
+        text.sections[0].value = roster.species[species.0].glyph.clone();
+        text.sections[0].style.color = theme.color(species.0);
     }
 }
 
+/// Swap each converted mesh entity to its new species' mesh and material.
+fn sync_shapes(
+    mut entities: Query<(&Species, &mut Mesh2dHandle, &mut Handle<ShapeMaterial>), Changed<Species>>,
+    shapes: Res<ShapeAssets>,
+) {
+    for (species, mut mesh, mut material) in &mut entities {
+        *mesh = shapes.meshes[species.0].clone();
+        *material = shapes.materials[species.0].clone();
+    }
+}
+
+/// Toggle between glyph and shape rendering when `M` is pressed, respawning the
+/// combatants in the other mode while preserving their species and position.
+fn toggle_render_mode(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    mut mode: ResMut<RenderMode>,
+    entities: Query<(Entity, &Species, &Transform)>,
+    roster: Res<Roster>,
+    themes: Res<Themes>,
+    shapes: Res<ShapeAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    if !keyboard.just_pressed(KeyCode::M) {
+        return;
+    }
+    *mode = match *mode {
+        RenderMode::Glyph => RenderMode::Shape,
+        RenderMode::Shape => RenderMode::Glyph,
+    };
+    let theme = themes.current();
+    for (entity, species, transform) in &entities {
+        commands.entity(entity).despawn();
+        spawn_combatant(
+            &mut commands,
+            *mode,
+            *species,
+            *transform,
+            &roster,
+            theme,
+            &shapes,
+            &asset_server,
+        );
+    }
+}
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<SimConfig>,
+    roster: Res<Roster>,
+    themes: Res<Themes>,
+    mode: Res<RenderMode>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ShapeMaterial>>,
+) {
+    commands.spawn(Camera2dBundle::default());
+    let theme = themes.current();
+    let shapes = ShapeAssets::build(&config, &roster, theme, &mut meshes, &mut materials);
+    let mut rng = thread_rng();
+    for _ in 0..config.entity_count {
+        let species = Species(Uniform::new(0, roster.len()).sample(&mut rng));
+        let transform = Transform::from_translation({
+            let theta = (Uniform::new(0., 360.).sample(&mut rng) as f32).to_radians();
+            let radius =
+                Uniform::new(config.spawn_radius_min, config.spawn_radius_max).sample(&mut rng);
+            Vec3::new(radius * theta.cos(), radius * theta.sin(), 0.0)
+        });
+        spawn_combatant(
+            &mut commands,
+            *mode,
+            species,
+            transform,
+            &roster,
+            theme,
+            &shapes,
+            &asset_server,
+        );
+    }
+    commands.insert_resource(shapes);
+}
+
+/// Map a world position to the integer coordinates of its spatial-hash cell.
+/// The cell side equals the interaction radius so that any pair within range
+/// lands in the same or an adjacent cell.
+fn cell_of(translation: Vec3, cell_size: f32) -> (i32, i32) {
+    (
+        (translation.x / cell_size).floor() as i32,
+        (translation.y / cell_size).floor() as i32,
+    )
+}
+
+/// Find the nearest entity this one has a dominance relation with (so the
+/// movement direction is never `Equal`), using the spatial-hash `grid`,
+/// spiralling outward ring by ring and stopping as soon as the best candidate
+/// is closer than anything a further ring could possibly hold.
+fn nearest_enemy(
+    grid: &HashMap<(i32, i32), Vec<usize>>,
+    copy: &[(Species, Transform)],
+    roster: &Roster,
+    bounds: Option<(i32, i32, i32, i32)>,
+    cell_size: f32,
+    index: usize,
+    this_species: Species,
+    this_translation: Vec3,
+) -> Option<usize> {
+    let (min_x, max_x, min_y, max_y) = bounds?;
+    let home = cell_of(this_translation, cell_size);
+    let max_ring = (home.0 - min_x)
+        .max(max_x - home.0)
+        .max(home.1 - min_y)
+        .max(max_y - home.1);
+    let mut best: Option<(usize, f32)> = None;
+    for ring in 0..=max_ring {
+        let ring_min = if ring == 0 { 0. } else { (ring - 1) as f32 * cell_size };
+        if let Some((_, best_distance)) = best {
+            if ring_min > best_distance {
+                break;
+            }
+        }
+        for dx in -ring..=ring {
+            for dy in -ring..=ring {
+                if dx.abs().max(dy.abs()) != ring {
+                    continue;
+                }
+                let Some(cell) = grid.get(&(home.0 + dx, home.1 + dy)) else {
+                    continue;
+                };
+                for &other in cell {
+                    if other == index
+                        || roster.order(this_species, copy[other].0) == Ordering::Equal
+                    {
+                        continue;
+                    }
+                    let distance = copy[other].1.translation.distance(this_translation);
+                    if best.map_or(true, |(_, best_distance)| distance < best_distance) {
+                        best = Some((other, distance));
+                    }
+                }
+            }
+        }
+    }
+    best.map(|(other, _)| other)
+}
+
 fn simulate(
-    mut entities: Query<(&mut Shape, &mut Transform, &mut Text)>,
+    mut entities: Query<(&mut Species, &mut Transform)>,
     time: Res<Time>,
-    settings: Res<Settings>,
+    config: Res<SimConfig>,
+    roster: Res<Roster>,
     mut gizmos: Gizmos,
 ) {
+    let cell_size = config.interaction_radius;
     let copy: Vec<_> = entities
         .iter()
-        .map(|(shape, transform, _)| (*shape, *transform))
+        .map(|(species, transform)| (*species, *transform))
         .collect();
-    for (this_shape, this_transform, _) in &mut entities {
-        let Some(closest) = copy
-            .iter()
-            .filter(|(shape, _)| shape != this_shape.as_ref())
-            .min_by(|(_, first_transform), (_, second_transform)| {
-                first_transform
-                    .translation
-                    .distance(this_transform.translation)
-                    .total_cmp(
-                        &second_transform
-                            .translation
-                            .distance(this_transform.translation),
-                    )
-            })
-        else {
+
+    // Bucket every entity index into its cell, then remember the occupied
+    // extent so the spiral search below knows when it has run out of rings.
+    let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (index, (_, transform)) in copy.iter().enumerate() {
+        grid.entry(cell_of(transform.translation, cell_size))
+            .or_default()
+            .push(index);
+    }
+    let bounds = grid.keys().fold(None, |acc, &(x, y)| {
+        let (min_x, max_x, min_y, max_y) =
+            acc.unwrap_or((x, x, y, y));
+        Some((min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y)))
+    });
+
+    for (index, (this_species, this_transform)) in (&mut entities).into_iter().enumerate() {
+        let this_translation = this_transform.translation;
+        let home = cell_of(this_translation, cell_size);
+        let Some(closest_index) = nearest_enemy(
+            &grid,
+            &copy,
+            &roster,
+            bounds,
+            cell_size,
+            index,
+            *this_species,
+            this_translation,
+        ) else {
             continue;
         };
-        let this_translation = this_transform.translation;
+        let closest = &copy[closest_index];
         // gizmos.ray(
         //     this_translation,
         //     (closest.1.translation - this_translation).normalize()
@@ -138,8 +709,8 @@ fn simulate(
         this_transform.into_inner().translation = (this_translation
             + (closest.1.translation - this_translation).normalize()
                 * time.delta_seconds()
-                * settings.speed
-                * match this_shape.cmp(&closest.0) {
+                * config.speed
+                * match roster.order(*this_species, closest.0) {
                     Ordering::Greater => 1.,
                     Ordering::Less => -1.,
                     Ordering::Equal => unreachable!(),
@@ -147,48 +718,111 @@ fn simulate(
                 * random::<f32>()
             + {
                 let average = {
-                    let close_entities = copy.iter().filter_map(|(_, transform)| {
-                        if transform.translation.distance(this_translation) < 20. {
-                            Some(transform.translation)
-                        } else {
-                            None
-                        }
-                    });
+                    let close_entities = (-1..=1)
+                        .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+                        .filter_map(|(dx, dy)| grid.get(&(home.0 + dx, home.1 + dy)))
+                        .flatten()
+                        .filter_map(|&other| {
+                            let translation = copy[other].1.translation;
+                            (translation.distance(this_translation) < config.interaction_radius)
+                                .then_some(translation)
+                        });
                     close_entities.clone().sum::<Vec3>() / close_entities.count() as f32
                 };
-                (this_translation - average).normalize_or_zero() * time.delta_seconds() * 60.
+                (this_translation - average).normalize_or_zero()
+                    * time.delta_seconds()
+                    * config.separation_strength
             }
         )
-        .clamp_length_max(800.);
+        .clamp_length_max(config.clamp_bound);
     }
 
     let copy: Vec<_> = entities
         .iter()
-        .map(|(shape, transform, text)| (*shape, *transform, text.clone()))
+        .map(|(species, transform)| (*species, *transform))
         .collect();
-    for (this_shape, this_transform, this_text) in copy {
-        for (other_shape, other_transform, mut other_text) in &mut entities {
-            if this_shape == *other_shape {
-                continue;
+
+    // Rebuild the grid against the post-movement positions and resolve each
+    // conversion by consulting only the 3×3 block around the winner's cell.
+    let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (index, (_, transform)) in copy.iter().enumerate() {
+        grid.entry(cell_of(transform.translation, cell_size))
+            .or_default()
+            .push(index);
+    }
+    let mut conversions: Vec<Option<Species>> = vec![None; copy.len()];
+    for (index, (this_species, this_transform)) in copy.iter().enumerate() {
+        let home = cell_of(this_transform.translation, cell_size);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let Some(cell) = grid.get(&(home.0 + dx, home.1 + dy)) else {
+                    continue;
+                };
+                for &other in cell {
+                    let (other_species, other_transform) = &copy[other];
+                    if this_transform
+                        .translation
+                        .distance(other_transform.translation)
+                        < cell_size
+                        && roster.beats(*this_species, *other_species)
+                    {
+                        conversions[other] = Some(*this_species);
+                    }
+                }
             }
-            if this_transform
-                .translation
-                .distance(other_transform.translation)
-                < 20.
-                && this_shape > *other_shape
-            {
-                *other_shape.into_inner() = this_shape;
-                other_text.sections[0].value = this_text.sections[0].value.clone();
-                other_text.sections[0].style.color = this_text.sections[0].style.color;
+        }
+    }
+    // Write back only the species change; a dedicated sync system repaints the
+    // glyph or swaps the mesh/material to match, depending on the render mode.
+    for ((mut species, _), winner) in (&mut entities).into_iter().zip(conversions) {
+        if let Some(winning_species) = winner {
+            *species = winning_species;
+        }
+    }
+}
+
+/// Cycle to the next theme when `T` is pressed, repainting the background and
+/// every combatant to the new palette. Glyph entities have their text recoloured
+/// directly; the shared shape materials are recoloured in place so shape-mode
+/// combatants follow along too.
+fn switch_theme(
+    keyboard: Res<Input<KeyCode>>,
+    mut themes: ResMut<Themes>,
+    mut clear_color: ResMut<ClearColor>,
+    mut entities: Query<(&Species, &mut Text)>,
+    shapes: Option<Res<ShapeAssets>>,
+    mut materials: ResMut<Assets<ShapeMaterial>>,
+) {
+    if !keyboard.just_pressed(KeyCode::T) {
+        return;
+    }
+    themes.cycle();
+    let theme = themes.current();
+    clear_color.0 = theme.background;
+    for (species, mut text) in &mut entities {
+        text.sections[0].style.color = theme.color(species.0);
+    }
+    if let Some(shapes) = shapes {
+        for (index, handle) in shapes.materials.iter().enumerate() {
+            if let Some(material) = materials.get_mut(handle) {
+                material.fill.color = theme.color(index).rgba_to_vec4();
             }
         }
     }
 }
 
 fn main() {
+    let config = SimConfig::load();
+    let roster = Roster::from_config(&config);
+    let themes = Themes::new(&config, &roster);
+    let background = themes.current().background;
+    let mode = RenderMode::from_config(&config);
     App::new()
-        .insert_resource(ClearColor(Color::BLACK))
-        .insert_resource(Settings { speed: 100. })
+        .insert_resource(ClearColor(background))
+        .insert_resource(config)
+        .insert_resource(roster)
+        .insert_resource(themes)
+        .insert_resource(mode)
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 mode: WindowMode::BorderlessFullscreen,
@@ -196,7 +830,18 @@ fn main() {
             }),
             ..default()
         }))
+        .add_plugins(Material2dPlugin::<ShapeMaterial>::default())
         .add_systems(Startup, startup)
-        .add_systems(Update, (simulate, close_on_esc))
+        .add_systems(
+            Update,
+            (
+                simulate,
+                toggle_render_mode,
+                sync_glyphs,
+                sync_shapes,
+                switch_theme,
+                close_on_esc,
+            ),
+        )
         .run();
 }